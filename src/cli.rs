@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+/// Läser en SIE-fil och skriver ut eller exporterar dess innehåll.
+#[derive(Parser)]
+#[command(name = "siedit", about = "Läser och exporterar SIE-filer")]
+pub struct Cli {
+	/// Sökväg till SIE-filen som ska läsas.
+	pub input: PathBuf,
+
+	/// Utdataformat. `sie` serialiserar tillbaka till en SIE-fil via `--output`.
+	#[arg(long, value_enum, default_value_t = Format::Debug)]
+	pub format: Format,
+
+	/// Fil att skriva resultatet till (csv, json, sie). Saknas den skrivs json till
+	/// stdout, csv till `output.csv` och sie till `output.se`. Debug skrivs alltid till stdout.
+	#[arg(long)]
+	pub output: Option<PathBuf>,
+
+	/// Begränsa export till ett enskilt konto.
+	#[arg(long)]
+	pub account: Option<u32>,
+
+	/// Begränsa export till en enskild serie.
+	#[arg(long)]
+	pub serie: Option<String>,
+
+	/// Starta HTTP-API:t istället för att exportera.
+	#[arg(long)]
+	pub serve: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+	Debug,
+	Csv,
+	Json,
+	Sie,
+}
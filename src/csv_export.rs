@@ -0,0 +1,152 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::models::{Account, Verification};
+
+fn csv_field(s: &str) -> String {
+	if s.contains(',') || s.contains('"') || s.contains('\n') {
+		format!("\"{}\"", s.replace('"', "\"\""))
+	} else {
+		s.to_string()
+	}
+}
+
+/// Flattar verifikationer till en rad per transaktion:
+/// `serie,number,date,text,account,account_name,amount`.
+pub fn write_transactions_csv<P: AsRef<Path>>(
+	path: P,
+	verifications: &Vec<Verification>,
+	accounts: &Vec<Account>,
+	account_filter: Option<u32>,
+	serie_filter: Option<&str>,
+) -> io::Result<()> {
+	let mut out = String::from("serie,number,date,text,account,account_name,amount\n");
+
+	for verification in verifications {
+		if let Some(serie) = serie_filter {
+			if verification.serie != serie {
+				continue;
+			}
+		}
+		for transaction in &verification.transactions {
+			if let Some(account) = account_filter {
+				if transaction.account != account {
+					continue;
+				}
+			}
+
+			let account_name = accounts
+				.iter()
+				.find(|a| a.number == transaction.account)
+				.map(|a| a.name.as_str())
+				.unwrap_or("");
+
+			out.push_str(&format!(
+				"{},{},{},{},{},{},{}\n",
+				csv_field(&verification.serie),
+				verification.number,
+				verification.date.format("%Y%m%d"),
+				csv_field(&verification.text),
+				transaction.account,
+				csv_field(account_name),
+				transaction.amount
+			));
+		}
+	}
+
+	fs::write(path, out)
+}
+
+/// Kontosammanfattning: `number,name,in_balance,out_balance`.
+pub fn write_accounts_csv<P: AsRef<Path>>(
+	path: P,
+	accounts: &Vec<Account>,
+	account_filter: Option<u32>,
+) -> io::Result<()> {
+	let mut out = String::from("number,name,in_balance,out_balance\n");
+
+	for account in accounts {
+		if let Some(filter) = account_filter {
+			if account.number != filter {
+				continue;
+			}
+		}
+
+		out.push_str(&format!(
+			"{},{},{},{}\n",
+			account.number,
+			csv_field(&account.name),
+			account.in_balances.get(&0).unwrap_or(&0.0),
+			account.out_balances.get(&0).unwrap_or(&0.0)
+		));
+	}
+
+	fs::write(path, out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models::Transaction;
+	use chrono::NaiveDate;
+	use std::collections::HashMap;
+
+	fn sample_data() -> (Vec<Account>, Vec<Verification>) {
+		let accounts = vec![
+			Account {
+				number: 1930,
+				name: "Bank".to_string(),
+				in_balances: HashMap::new(),
+				out_balances: HashMap::new(),
+			},
+			Account {
+				number: 3010,
+				name: "Försäljning".to_string(),
+				in_balances: HashMap::new(),
+				out_balances: HashMap::new(),
+			},
+		];
+		let verifications = vec![Verification {
+			serie: "A".to_string(),
+			number: 1,
+			date: NaiveDate::parse_from_str("20240115", "%Y%m%d").unwrap(),
+			text: "Försäljning".to_string(),
+			transactions: vec![
+				Transaction { account: 1930, amount: 100.0 },
+				Transaction { account: 3010, amount: -100.0 },
+			],
+		}];
+
+		(accounts, verifications)
+	}
+
+	#[test]
+	fn write_transactions_csv_emits_one_row_per_transaction() {
+		let (accounts, verifications) = sample_data();
+		let path = std::env::temp_dir().join(format!("siedit_csv_test_{}.csv", std::process::id()));
+
+		write_transactions_csv(&path, &verifications, &accounts, None, None).unwrap();
+		let contents = fs::read_to_string(&path).unwrap();
+		fs::remove_file(&path).ok();
+
+		let mut lines = contents.lines();
+		assert_eq!(lines.next().unwrap(), "serie,number,date,text,account,account_name,amount");
+		assert_eq!(lines.next().unwrap(), "A,1,20240115,Försäljning,1930,Bank,100");
+		assert_eq!(lines.next().unwrap(), "A,1,20240115,Försäljning,3010,Försäljning,-100");
+		assert_eq!(lines.next(), None);
+	}
+
+	#[test]
+	fn write_transactions_csv_applies_account_filter() {
+		let (accounts, verifications) = sample_data();
+		let path = std::env::temp_dir().join(format!("siedit_csv_filter_test_{}.csv", std::process::id()));
+
+		write_transactions_csv(&path, &verifications, &accounts, Some(1930), None).unwrap();
+		let contents = fs::read_to_string(&path).unwrap();
+		fs::remove_file(&path).ok();
+
+		let rows: Vec<&str> = contents.lines().skip(1).collect();
+		assert_eq!(rows, vec!["A,1,20240115,Försäljning,1930,Bank,100"]);
+	}
+}
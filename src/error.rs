@@ -0,0 +1,60 @@
+use std::fmt;
+use std::io;
+use chrono::NaiveDate;
+
+/// Fel som kan uppstå vid inläsning och tolkning av en SIE-fil.
+///
+/// Skiljer på fel som gör filen helt oläsbar (`Io`, `MalformedRecord` för
+/// fält som krävs för att fortsätta, t.ex. `#VER`s egna fält) och fel som rör
+/// enskilda poster men som ändå tillåter resten av filen att tolkas
+/// (`UnknownAccount`, `Unbalanced`, `OutOfPeriod`, och `MalformedRecord` för
+/// valfria/återhämtningsbara poster som en `#TRANS`-rad utan läsbart belopp)
+/// — de senare samlas upp som varningar av `parse_sie_file` istället för att
+/// avbryta tolkningen.
+///
+/// `Decode`-fel finns avsiktligt inte: `WINDOWS_1252` är en fullständig
+/// enbytekodning där varje byte avkodas till något tecken, så avkodning kan
+/// aldrig misslyckas (till skillnad från `Encode` vid export, där en sträng
+/// kan innehålla tecken som saknar motsvarighet i målkodsidan).
+#[derive(Debug)]
+pub enum SieError {
+	Io(io::Error),
+	MalformedRecord { line_no: usize, record: String, raw: String },
+	UnknownAccount { account: u32, line_no: usize },
+	Unbalanced { serie: String, number: u32, diff: f64 },
+	OutOfPeriod { serie: String, number: u32, date: NaiveDate },
+	Encode { raw: String },
+}
+
+impl fmt::Display for SieError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SieError::Io(e) => write!(f, "IO-fel: {}", e),
+			SieError::MalformedRecord { line_no, record, raw } => {
+				write!(f, "Felaktig {} post på rad {}: {}", record, line_no, raw)
+			}
+			SieError::UnknownAccount { account, line_no } => {
+				write!(f, "Okänt konto {} på rad {}", account, line_no)
+			}
+			SieError::Unbalanced { serie, number, diff } => write!(
+				f,
+				"Verifikation {} {} balanserar inte, diff: {:.2}",
+				serie, number, diff
+			),
+			SieError::OutOfPeriod { serie, number, date } => write!(
+				f,
+				"Verifikation {} {} har datum {} utanför innevarande räkenskapsår",
+				serie, number, date
+			),
+			SieError::Encode { raw } => write!(f, "Kunde inte koda om tecken vid export: {}", raw),
+		}
+	}
+}
+
+impl std::error::Error for SieError {}
+
+impl From<io::Error> for SieError {
+	fn from(e: io::Error) -> Self {
+		SieError::Io(e)
+	}
+}
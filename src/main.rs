@@ -1,38 +1,39 @@
+mod cli;
+mod csv_export;
+mod error;
+mod models;
+mod server;
+mod writer;
+
 use std::path::Path;
 use std::fs;
 use std::io;
+use clap::Parser;
 use encoding_rs::WINDOWS_1252;
+use chrono::NaiveDate;
+use serde::Serialize;
 
-#[derive(Debug)]
-struct Account {
-	number: u32,
-	name: String,
-	in_balance: f64,
-	out_balance: f64,
-}
+use cli::{Cli, Format};
+use error::SieError;
+use models::{Account, FiscalYear, Transaction, Verification};
 
-#[derive(Debug)]
-struct Verification {
-	serie: String,
-	number: u32,
-	date: String,
-	text: String,
-	transactions: Vec<Transaction>,
-}
+// Tillåten avvikelse för en balanserad verifikation, för att absorbera öresavrundning.
+const BALANCE_EPSILON: f64 = 0.005;
 
-#[derive(Debug)]
-struct Transaction {
-	account: u32,
-	amount: f64,
+struct ParsedSieFile {
+	verifications: Vec<Verification>,
+	accounts: Vec<Account>,
+	fiscal_years: Vec<FiscalYear>,
+	warnings: Vec<SieError>,
 }
 
-fn read_sie_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+fn read_sie_file<P: AsRef<Path>>(path: P) -> Result<String, SieError> {
 	let raw_bytes = fs::read(path)?;
 	let (cow, _, _) = WINDOWS_1252.decode(&raw_bytes);
 	Ok(cow.into_owned())
 }
 
-fn clean_string(s: String) -> String {
+fn clean_string(s: &str) -> String {
 	s.trim_matches('"')
 		.trim()
 		.replace('„', "ä") // fallback för felteckenkodningar
@@ -43,10 +44,16 @@ fn clean_string(s: String) -> String {
 		.replace('�', "?") // osäkra tecken
 }
 
+/// Delar upp en SIE-rad i fält på mellanslag, med citationstecken för fält som
+/// innehåller mellanslag. Är dessutom klammermedveten: mellanslag inuti en
+/// `{...}`-objektlista (t.ex. `{1 "100"}` i en `#TRANS`-rad) håller ihop
+/// objektlistan som ett enda fält istället för att splitta den, så att fält
+/// efter listan (belopp, transdat, transtext, ...) hamnar på rätt index.
 fn parse_line(s: &str) -> Vec<String> {
 	let mut ret : Vec<String> = Vec::new();
 
 	let mut inside_quote : bool = false;
+	let mut brace_depth : u32 = 0;
 	let mut current_string : String = String::new();
 	for ch in s.chars() {
 		match ch {
@@ -57,8 +64,16 @@ fn parse_line(s: &str) -> Vec<String> {
 					inside_quote = true;
 				}
 			},
+			'{' if !inside_quote => {
+				brace_depth += 1;
+				current_string.push(ch);
+			},
+			'}' if !inside_quote => {
+				brace_depth = brace_depth.saturating_sub(1);
+				current_string.push(ch);
+			},
 			' ' => {
-				if inside_quote {
+				if inside_quote || brace_depth > 0 {
 					// just read.
 					current_string.push(ch);
 				} else {
@@ -77,6 +92,39 @@ fn parse_line(s: &str) -> Vec<String> {
 	return ret;
 }
 
+/// Hämtar fält `idx` ur en tolkad rad, eller ett `MalformedRecord`-fel om raden
+/// är för kort för att innehålla det fältet.
+fn field<'a>(parts: &'a [String], idx: usize, line_no: usize, record: &str, raw: &str) -> Result<&'a str, SieError> {
+	parts
+		.get(idx)
+		.map(|s| s.as_str())
+		.ok_or_else(|| SieError::MalformedRecord {
+			line_no,
+			record: record.to_string(),
+			raw: raw.to_string(),
+		})
+}
+
+/// Som `field`, men tolkar fältet som `T` via `FromStr`.
+fn parse_field<T: std::str::FromStr>(parts: &[String], idx: usize, line_no: usize, record: &str, raw: &str) -> Result<T, SieError> {
+	field(parts, idx, line_no, record, raw)?
+		.parse::<T>()
+		.map_err(|_| SieError::MalformedRecord {
+			line_no,
+			record: record.to_string(),
+			raw: raw.to_string(),
+		})
+}
+
+/// Tolkar `s` som ett `YYYYMMDD`-datum.
+fn parse_date(s: &str, line_no: usize, record: &str, raw: &str) -> Result<NaiveDate, SieError> {
+	NaiveDate::parse_from_str(s, "%Y%m%d").map_err(|_| SieError::MalformedRecord {
+		line_no,
+		record: record.to_string(),
+		raw: raw.to_string(),
+	})
+}
+
 fn find_account(accounts : &Vec<Account>, number : u32) -> Option<usize> {
 	for (idx, account) in accounts.iter().enumerate() {
 		if account.number == number {
@@ -86,79 +134,170 @@ fn find_account(accounts : &Vec<Account>, number : u32) -> Option<usize> {
 	return None;
 }
 
-fn calculate_out_balances(accounts : &mut Vec<Account>, verifications : &Vec<Verification>) {
+/// Räknar om utgående balans för innevarande räkenskapsår (index 0) från
+/// ingående balans plus transaktioner. Detta skriver alltid över ett eventuellt
+/// `#UB 0 ...`-värde som redan lästs in från filen — den beräknade balansen
+/// är facit, inte filens, eftersom en korrupt eller förlegad #UB-rad annars
+/// tyst skulle dölja en felaktig verifikation.
+fn calculate_out_balances(accounts : &mut Vec<Account>, verifications : &Vec<Verification>) -> Vec<SieError> {
+	let mut warnings = Vec::new();
+
 	for account in accounts.iter_mut() {
-		account.out_balance = account.in_balance;
+		let opening = *account.in_balances.get(&0).unwrap_or(&0.0);
+		account.out_balances.insert(0, opening);
 	}
 	for verification in verifications {
 		for transaction in &verification.transactions {
 			match find_account(&accounts, transaction.account) {
 				Some(idx) => {
-					accounts[idx].out_balance += transaction.amount;
+					*accounts[idx].out_balances.entry(0).or_insert(0.0) += transaction.amount;
 				},
 				None => {
-					println!("Could not find account for transaction {}, {:?}", transaction.account, transaction);
+					warnings.push(SieError::UnknownAccount {
+						account: transaction.account,
+						line_no: 0,
+					});
 				},
 			}
 		}
 	}
+
+	warnings
 }
 
-fn parse_sie_file<P: AsRef<Path>>(path: P) -> io::Result<(Vec<Verification>, Vec<Account>)> {
+fn validate_balances(verifications: &Vec<Verification>) -> Vec<SieError> {
+	let mut warnings = Vec::new();
+
+	for verification in verifications {
+		let sum: f64 = verification.transactions.iter().map(|t| t.amount).sum();
+		if sum.abs() > BALANCE_EPSILON {
+			warnings.push(SieError::Unbalanced {
+				serie: verification.serie.clone(),
+				number: verification.number,
+				diff: sum,
+			});
+		}
+	}
+
+	warnings
+}
+
+/// Flaggar verifikationer vars datum ligger utanför innevarande räkenskapsår
+/// (`#RAR`-index 0). Om filen saknar `#RAR`-poster görs ingen kontroll.
+fn validate_fiscal_period(verifications: &Vec<Verification>, fiscal_years: &Vec<FiscalYear>) -> Vec<SieError> {
+	let mut warnings = Vec::new();
+
+	if let Some(current) = fiscal_years.iter().find(|fy| fy.index == 0) {
+		for verification in verifications {
+			if verification.date < current.start || verification.date > current.end {
+				warnings.push(SieError::OutOfPeriod {
+					serie: verification.serie.clone(),
+					number: verification.number,
+					date: verification.date,
+				});
+			}
+		}
+	}
+
+	warnings
+}
+
+fn parse_sie_file<P: AsRef<Path>>(path: P) -> Result<ParsedSieFile, SieError> {
 	let contents = read_sie_file(path)?;
 
 	let mut verifications = Vec::new();
 	let mut accounts = Vec::new();
+	let mut fiscal_years = Vec::new();
 	let mut current_ver: Option<Verification> = None;
+	let mut warnings: Vec<SieError> = Vec::new();
 
-	for line in contents.lines() {
-
+	for (line_no, line) in contents.lines().enumerate() {
 		if line.starts_with("#VER") {
 			if let Some(ver) = current_ver.take() {
 				verifications.push(ver);
 			}
 
 			let parts: Vec<String> = parse_line(line);
-			println!("{:?}", parts);
 			current_ver = Some(Verification {
-				serie: clean_string(parts[1].clone()),
-				number: parts[2].parse().unwrap_or(0),
-				date: clean_string(parts[3].clone()),
-				text: clean_string(parts[4].clone()),
+				serie: clean_string(field(&parts, 1, line_no, "#VER", line)?),
+				number: parse_field(&parts, 2, line_no, "#VER", line)?,
+				date: parse_date(field(&parts, 3, line_no, "#VER", line)?, line_no, "#VER", line)?,
+				text: clean_string(field(&parts, 4, line_no, "#VER", line)?),
 				transactions: Vec::new(),
 			});
-		} else if line.starts_with("#IB") {
+		} else if line.starts_with("#RAR") {
+			let parts: Vec<String> = parse_line(line);
 
+			let index: i32 = parse_field(&parts, 1, line_no, "#RAR", line)?;
+			let start = parse_date(field(&parts, 2, line_no, "#RAR", line)?, line_no, "#RAR", line)?;
+			let end = parse_date(field(&parts, 3, line_no, "#RAR", line)?, line_no, "#RAR", line)?;
+
+			fiscal_years.push(FiscalYear { index, start, end });
+		} else if line.starts_with("#IB") {
 			let parts: Vec<String> = parse_line(line);
 
-			let year : u32 = parts[1].parse().unwrap_or(0);
-			let account_no : u32 = parts[2].parse().unwrap_or(0);
-			let amount : f64 = parts[3].parse().unwrap_or(0.0);
-
-			if year == 0 {
-				match find_account(&accounts, account_no) {
-					Some(idx) => {
-						accounts[idx].in_balance = amount;
-					},
-					None => {
-						println!("Cannot find account {}", account_no);
-					}
+			let year: i32 = parse_field(&parts, 1, line_no, "#IB", line)?;
+			let account_no: u32 = parse_field(&parts, 2, line_no, "#IB", line)?;
+			let amount: f64 = parse_field(&parts, 3, line_no, "#IB", line)?;
+
+			match find_account(&accounts, account_no) {
+				Some(idx) => {
+					accounts[idx].in_balances.insert(year, amount);
+				},
+				None => {
+					warnings.push(SieError::UnknownAccount { account: account_no, line_no });
 				}
 			}
+		} else if line.starts_with("#UB") {
+			// #UB 0 (innevarande år) hamnar här men skrivs över av
+			// calculate_out_balances nedan, som räknar om den från IB + transaktioner.
+			let parts: Vec<String> = parse_line(line);
+
+			let year: i32 = parse_field(&parts, 1, line_no, "#UB", line)?;
+			let account_no: u32 = parse_field(&parts, 2, line_no, "#UB", line)?;
+			let amount: f64 = parse_field(&parts, 3, line_no, "#UB", line)?;
 
+			match find_account(&accounts, account_no) {
+				Some(idx) => {
+					accounts[idx].out_balances.insert(year, amount);
+				},
+				None => {
+					warnings.push(SieError::UnknownAccount { account: account_no, line_no });
+				}
+			}
 		} else if line.starts_with("#KONTO") {
 			let parts: Vec<String> = parse_line(line);
-			let name : String = clean_string(parts[2].clone());
-			let number : u32 = parts[1].parse().unwrap_or(0);
-			let in_balance : f64 = 0.0;
-			let out_balance : f64 = 0.0;
-			accounts.push(Account {number, name, in_balance, out_balance});
+			let number: u32 = parse_field(&parts, 1, line_no, "#KONTO", line)?;
+			let name: String = clean_string(field(&parts, 2, line_no, "#KONTO", line)?);
+			accounts.push(Account {
+				number,
+				name,
+				in_balances: std::collections::HashMap::new(),
+				out_balances: std::collections::HashMap::new(),
+			});
 		} else if line.starts_with("#TRANS") {
 			if let Some(ref mut ver) = current_ver {
 				let parts: Vec<String> = parse_line(line);
-				let account = parts[1].parse().unwrap_or(0);
-				let amount: f64 = parts.last().unwrap().parse().unwrap_or(0.0);
-				ver.transactions.push(Transaction { account, amount });
+				let account: u32 = parse_field(&parts, 1, line_no, "#TRANS", line)?;
+
+				// belopp är fältet direkt efter objektlistan `{...}`, oavsett hur
+				// många valfria fält (transdat, transtext, kvantitet, ...) som
+				// följer efter det. En rad utan belopp på rätt plats är en
+				// enskild felaktig post, inte ett skäl att kasta hela filen.
+				let amount = parts
+					.iter()
+					.position(|p| p.starts_with('{'))
+					.and_then(|idx| parts.get(idx + 1))
+					.and_then(|raw| raw.parse::<f64>().ok());
+
+				match amount {
+					Some(amount) => ver.transactions.push(Transaction { account, amount }),
+					None => warnings.push(SieError::MalformedRecord {
+						line_no,
+						record: "#TRANS".to_string(),
+						raw: line.to_string(),
+					}),
+				}
 			}
 		}
 	}
@@ -167,25 +306,220 @@ fn parse_sie_file<P: AsRef<Path>>(path: P) -> io::Result<(Vec<Verification>, Vec
 		verifications.push(ver);
 	}
 
-	calculate_out_balances(&mut accounts, &verifications);
+	warnings.extend(calculate_out_balances(&mut accounts, &verifications));
+	warnings.extend(validate_balances(&verifications));
+	warnings.extend(validate_fiscal_period(&verifications, &fiscal_years));
 
-	Ok((verifications, accounts))
+	Ok(ParsedSieFile { verifications, accounts, fiscal_years, warnings })
 }
 
-fn main() {
-	let path = "cc.se";
-	match parse_sie_file(path) {
-		Ok((verifications, accounts)) => {
-			for ver in verifications {
+#[derive(Serialize)]
+struct JsonExport<'a> {
+	accounts: &'a Vec<Account>,
+	verifications: &'a Vec<Verification>,
+	fiscal_years: &'a Vec<FiscalYear>,
+	warnings: Vec<String>,
+}
+
+/// Behåller bara verifikationer vars serie matchar `serie` (om satt), och
+/// inom dem bara transaktioner mot `account` (om satt). En verifikation utan
+/// kvarvarande transaktioner efter ett kontofilter tas bort helt. Samma regel
+/// som `csv_export` tillämpar på sina rader, så `--account`/`--serie` ger
+/// samma resultat oavsett `--format`.
+fn filter_verifications(verifications: &Vec<Verification>, account: Option<u32>, serie: Option<&str>) -> Vec<Verification> {
+	verifications
+		.iter()
+		.filter(|v| serie.map_or(true, |s| v.serie == s))
+		.filter_map(|v| {
+			let transactions: Vec<Transaction> = v
+				.transactions
+				.iter()
+				.filter(|t| account.map_or(true, |a| t.account == a))
+				.cloned()
+				.collect();
+
+			if account.is_some() && transactions.is_empty() {
+				None
+			} else {
+				Some(Verification { transactions, ..v.clone() })
+			}
+		})
+		.collect()
+}
+
+/// Behåller bara konton som matchar `account` (om satt).
+fn filter_accounts(accounts: &Vec<Account>, account: Option<u32>) -> Vec<Account> {
+	accounts
+		.iter()
+		.filter(|a| account.map_or(true, |n| a.number == n))
+		.cloned()
+		.collect()
+}
+
+fn run_export(cli: &Cli, parsed: &ParsedSieFile) -> io::Result<()> {
+	match cli.format {
+		Format::Debug => {
+			let verifications = filter_verifications(&parsed.verifications, cli.account, cli.serie.as_deref());
+			let accounts = filter_accounts(&parsed.accounts, cli.account);
+
+			for ver in &verifications {
 				println!("{:?}", ver);
 			}
-			for account in accounts {
-				if account.in_balance != 0.0 || account.out_balance != 0.0 {
+			for account in &accounts {
+				let has_balance = account.in_balances.values().any(|v| *v != 0.0)
+					|| account.out_balances.values().any(|v| *v != 0.0);
+				if has_balance {
 					println!("{:?}", account);
 				}
 			}
+			for fiscal_year in &parsed.fiscal_years {
+				println!("{:?}", fiscal_year);
+			}
+			for warning in &parsed.warnings {
+				println!("{}", warning);
+			}
+			Ok(())
+		}
+		Format::Csv => {
+			let output = cli.output.clone().unwrap_or_else(|| Path::new("output.csv").to_path_buf());
+			csv_export::write_transactions_csv(
+				&output,
+				&parsed.verifications,
+				&parsed.accounts,
+				cli.account,
+				cli.serie.as_deref(),
+			)?;
+
+			let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+			let accounts_path = output.with_file_name(format!("{}.accounts.csv", stem));
+			csv_export::write_accounts_csv(&accounts_path, &parsed.accounts, cli.account)?;
+
+			println!("Skrev {} och {}", output.display(), accounts_path.display());
+			Ok(())
+		}
+		Format::Sie => {
+			let output = cli.output.clone().unwrap_or_else(|| Path::new("output.se").to_path_buf());
+			let verifications = filter_verifications(&parsed.verifications, cli.account, cli.serie.as_deref());
+			let accounts = filter_accounts(&parsed.accounts, cli.account);
+			writer::write_sie_file(&output, &accounts, &verifications)
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+			println!("Skrev {}", output.display());
+			Ok(())
+		}
+		Format::Json => {
+			let verifications = filter_verifications(&parsed.verifications, cli.account, cli.serie.as_deref());
+			let accounts = filter_accounts(&parsed.accounts, cli.account);
+			let export = JsonExport {
+				accounts: &accounts,
+				verifications: &verifications,
+				fiscal_years: &parsed.fiscal_years,
+				warnings: parsed.warnings.iter().map(|w| w.to_string()).collect(),
+			};
+			let json = serde_json::to_string_pretty(&export).unwrap_or_default();
+
+			match &cli.output {
+				Some(path) => fs::write(path, json)?,
+				None => println!("{}", json),
+			}
+			Ok(())
+		}
+	}
+}
+
+fn main() {
+	let cli = Cli::parse();
+
+	match parse_sie_file(&cli.input) {
+		Ok(parsed) => {
+			if cli.serve {
+				let rt = tokio::runtime::Runtime::new().expect("kunde inte starta tokio-runtime");
+				rt.block_on(server::run(parsed.accounts, parsed.verifications));
+				return;
+			}
+
+			if let Err(e) = run_export(&cli, &parsed) {
+				eprintln!("Fel vid export: {}", e);
+			}
 		}
 		Err(e) => eprintln!("Fel vid inläsning: {}", e),
 	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn verification(serie: &str, number: u32, date: &str, amounts: &[f64]) -> Verification {
+		Verification {
+			serie: serie.to_string(),
+			number,
+			date: NaiveDate::parse_from_str(date, "%Y%m%d").unwrap(),
+			text: "test".to_string(),
+			transactions: amounts
+				.iter()
+				.map(|amount| Transaction { account: 1930, amount: *amount })
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn validate_balances_flags_unbalanced_verification() {
+		let verifications = vec![verification("A", 1, "20240101", &[100.0, -99.0])];
+
+		let warnings = validate_balances(&verifications);
+
+		assert_eq!(warnings.len(), 1);
+		match &warnings[0] {
+			SieError::Unbalanced { serie, number, diff } => {
+				assert_eq!(serie, "A");
+				assert_eq!(*number, 1);
+				assert!((diff - 1.0).abs() < BALANCE_EPSILON);
+			}
+			other => panic!("expected Unbalanced, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn validate_balances_accepts_balanced_verification() {
+		let verifications = vec![verification("A", 1, "20240101", &[100.0, -100.0])];
+
+		let warnings = validate_balances(&verifications);
+
+		assert!(warnings.is_empty());
+	}
+
+	fn fiscal_year(start: &str, end: &str) -> FiscalYear {
+		FiscalYear {
+			index: 0,
+			start: NaiveDate::parse_from_str(start, "%Y%m%d").unwrap(),
+			end: NaiveDate::parse_from_str(end, "%Y%m%d").unwrap(),
+		}
+	}
+
+	#[test]
+	fn validate_fiscal_period_flags_date_outside_current_year() {
+		let fiscal_years = vec![fiscal_year("20240101", "20241231")];
+		let verifications = vec![verification("A", 1, "20230615", &[100.0, -100.0])];
+
+		let warnings = validate_fiscal_period(&verifications, &fiscal_years);
+
+		assert_eq!(warnings.len(), 1);
+		match &warnings[0] {
+			SieError::OutOfPeriod { serie, number, .. } => {
+				assert_eq!(serie, "A");
+				assert_eq!(*number, 1);
+			}
+			other => panic!("expected OutOfPeriod, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn validate_fiscal_period_accepts_date_inside_current_year() {
+		let fiscal_years = vec![fiscal_year("20240101", "20241231")];
+		let verifications = vec![verification("A", 1, "20240615", &[100.0, -100.0])];
+
+		let warnings = validate_fiscal_period(&verifications, &fiscal_years);
+
+		assert!(warnings.is_empty());
+	}
+}
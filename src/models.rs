@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Account {
+	pub number: u32,
+	pub name: String,
+	/// Ingående balans per räkenskapsårsindex (0 = innevarande, -1 = föregående, osv).
+	pub in_balances: HashMap<i32, f64>,
+	/// Utgående balans per räkenskapsårsindex.
+	pub out_balances: HashMap<i32, f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+	pub serie: String,
+	pub number: u32,
+	pub date: NaiveDate,
+	pub text: String,
+	pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transaction {
+	pub account: u32,
+	pub amount: f64,
+}
+
+/// Ett räkenskapsår enligt `#RAR`. Index 0 är innevarande år, -1 föregående, osv.
+#[derive(Debug, Clone, Serialize)]
+pub struct FiscalYear {
+	pub index: i32,
+	pub start: NaiveDate,
+	pub end: NaiveDate,
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Account, Verification};
+
+#[derive(Clone)]
+struct AppState {
+	accounts: Arc<Vec<Account>>,
+	verifications: Arc<Vec<Verification>>,
+}
+
+/// Kontosammanfattning för HTTP-API:t: nummer, namn och innevarande
+/// in-/utbalans (räkenskapsårsindex 0).
+#[derive(Serialize)]
+struct AccountSummary {
+	number: u32,
+	name: String,
+	in_balance: f64,
+	out_balance: f64,
+}
+
+impl From<&Account> for AccountSummary {
+	fn from(account: &Account) -> Self {
+		AccountSummary {
+			number: account.number,
+			name: account.name.clone(),
+			in_balance: *account.in_balances.get(&0).unwrap_or(&0.0),
+			out_balance: *account.out_balances.get(&0).unwrap_or(&0.0),
+		}
+	}
+}
+
+async fn list_accounts(State(state): State<AppState>) -> Json<Vec<AccountSummary>> {
+	Json(state.accounts.iter().map(AccountSummary::from).collect())
+}
+
+async fn get_account(
+	State(state): State<AppState>,
+	Path(number): Path<u32>,
+) -> Result<Json<AccountSummary>, StatusCode> {
+	state
+		.accounts
+		.iter()
+		.find(|a| a.number == number)
+		.map(|a| Json(AccountSummary::from(a)))
+		.ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Deserialize)]
+struct VerificationsQuery {
+	serie: Option<String>,
+}
+
+async fn list_verifications(
+	State(state): State<AppState>,
+	Query(query): Query<VerificationsQuery>,
+) -> Json<Vec<Verification>> {
+	let filtered = state
+		.verifications
+		.iter()
+		.filter(|v| query.serie.as_ref().map_or(true, |serie| &v.serie == serie))
+		.cloned()
+		.collect();
+	Json(filtered)
+}
+
+#[derive(Deserialize)]
+struct BalanceQuery {
+	account: u32,
+	date: String,
+}
+
+#[derive(Serialize)]
+struct BalanceResponse {
+	account: u32,
+	date: String,
+	balance: f64,
+}
+
+/// Spelar upp transaktioner fram till och med `cutoff`, på samma sätt som
+/// `calculate_out_balances` men stoppat vid ett givet datum istället för
+/// hela filen.
+fn calculate_balance_as_of(
+	accounts: &Vec<Account>,
+	verifications: &Vec<Verification>,
+	account_number: u32,
+	cutoff: NaiveDate,
+) -> Option<f64> {
+	let mut balance = *accounts
+		.iter()
+		.find(|a| a.number == account_number)?
+		.in_balances
+		.get(&0)
+		.unwrap_or(&0.0);
+
+	for verification in verifications {
+		if verification.date > cutoff {
+			continue;
+		}
+		for transaction in &verification.transactions {
+			if transaction.account == account_number {
+				balance += transaction.amount;
+			}
+		}
+	}
+
+	Some(balance)
+}
+
+async fn get_balance(
+	State(state): State<AppState>,
+	Query(query): Query<BalanceQuery>,
+) -> Result<Json<BalanceResponse>, StatusCode> {
+	let cutoff = NaiveDate::parse_from_str(&query.date, "%Y%m%d").map_err(|_| StatusCode::BAD_REQUEST)?;
+	let balance = calculate_balance_as_of(&state.accounts, &state.verifications, query.account, cutoff)
+		.ok_or(StatusCode::NOT_FOUND)?;
+
+	Ok(Json(BalanceResponse { account: query.account, date: query.date, balance }))
+}
+
+/// Startar HTTP-API:t och blockerar tills servern stängs ner. Läser in
+/// modellen en gång och delar den mellan alla requests.
+pub async fn run(accounts: Vec<Account>, verifications: Vec<Verification>) {
+	let state = AppState {
+		accounts: Arc::new(accounts),
+		verifications: Arc::new(verifications),
+	};
+
+	let app = Router::new()
+		.route("/accounts", get(list_accounts))
+		.route("/accounts/:number", get(get_account))
+		.route("/verifications", get(list_verifications))
+		.route("/balance", get(get_balance))
+		.with_state(state);
+
+	let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+	println!("Lyssnar på http://0.0.0.0:3000");
+	axum::serve(listener, app).await.unwrap();
+}
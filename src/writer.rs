@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::Path;
+use encoding_rs::WINDOWS_1252;
+
+use crate::error::SieError;
+use crate::models::{Account, Verification};
+
+/// Citerar ett fält med dubbla citattecken om det innehåller mellanslag eller
+/// är tomt, precis som `parse_line` förväntar sig vid inläsning.
+fn quote_field(s: &str) -> String {
+	if s.is_empty() || s.contains(' ') {
+		format!("\"{}\"", s)
+	} else {
+		s.to_string()
+	}
+}
+
+fn format_amount(amount: f64) -> String {
+	format!("{:.2}", amount)
+}
+
+/// Skriver `#IB`/`#UB`-rader för ett konto i stigande årsindexordning, så att
+/// utskriften är deterministisk trots att balanserna lagras i en `HashMap`.
+///
+/// Observera att `out_balances[0]` alltid är den av `calculate_out_balances`
+/// omräknade utgående balansen för innevarande år (se `main.rs`), inte
+/// nödvändigtvis den `#UB 0`-rad som eventuellt fanns i källfilen — en
+/// inläst och sedan utskriven fil kan alltså få en annan `#UB 0`-balans än
+/// originalet om källfilens `#UB` och dess transaktioner inte stämmer överens.
+fn write_accounts(out: &mut String, accounts: &Vec<Account>) {
+	for account in accounts {
+		out.push_str(&format!("#KONTO {} {}\n", account.number, quote_field(&account.name)));
+	}
+
+	for account in accounts {
+		let mut in_years: Vec<&i32> = account.in_balances.keys().collect();
+		in_years.sort();
+		for year in in_years {
+			out.push_str(&format!(
+				"#IB {} {} {}\n",
+				year,
+				account.number,
+				format_amount(account.in_balances[year])
+			));
+		}
+
+		let mut out_years: Vec<&i32> = account.out_balances.keys().collect();
+		out_years.sort();
+		for year in out_years {
+			out.push_str(&format!(
+				"#UB {} {} {}\n",
+				year,
+				account.number,
+				format_amount(account.out_balances[year])
+			));
+		}
+	}
+}
+
+fn write_verifications(out: &mut String, verifications: &Vec<Verification>) {
+	for verification in verifications {
+		out.push_str(&format!(
+			"#VER {} {} {} {}\n",
+			quote_field(&verification.serie),
+			verification.number,
+			verification.date.format("%Y%m%d"),
+			quote_field(&verification.text)
+		));
+		out.push_str("{\n");
+		for transaction in &verification.transactions {
+			out.push_str(&format!(
+				"#TRANS {} {{}} {}\n",
+				transaction.account,
+				format_amount(transaction.amount)
+			));
+		}
+		out.push_str("}\n");
+	}
+}
+
+/// Serialiserar konton och verifikationer till en SIE-fil på `path`, kodad
+/// med samma kodsida (`WINDOWS_1252`) som `read_sie_file` avkodar från. En
+/// läst och sedan skriven fil blir *inte* nödvändigtvis byte-identisk med
+/// originalet: `clean_string` (se `main.rs`) normaliserar vissa felkodade
+/// citationstecken och symboler till å/ä/ö/Å/Ö redan vid inläsningen, så text
+/// som legitimt innehåller de tecknen tappar sin ursprungliga bytesekvens.
+pub fn write_sie_file<P: AsRef<Path>>(
+	path: P,
+	accounts: &Vec<Account>,
+	verifications: &Vec<Verification>,
+) -> Result<(), SieError> {
+	let mut out = String::new();
+
+	write_accounts(&mut out, accounts);
+	write_verifications(&mut out, verifications);
+
+	let (encoded, _, had_errors) = WINDOWS_1252.encode(&out);
+	if had_errors {
+		return Err(SieError::Encode { raw: out });
+	}
+
+	fs::write(path, encoded)?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models::Transaction;
+	use chrono::NaiveDate;
+	use std::collections::HashMap;
+
+	#[test]
+	fn encodes_swedish_characters_as_windows_1252() {
+		let mut in_balances = HashMap::new();
+		in_balances.insert(0, 1000.0);
+
+		let accounts = vec![Account {
+			number: 3010,
+			name: "Försäljning Åkerby, Örnsköldsvik".to_string(),
+			in_balances,
+			out_balances: HashMap::new(),
+		}];
+		let verifications = vec![Verification {
+			serie: "A".to_string(),
+			number: 1,
+			date: NaiveDate::parse_from_str("20240115", "%Y%m%d").unwrap(),
+			text: "Öppningsbalans för källaren".to_string(),
+			transactions: vec![Transaction { account: 3010, amount: 1000.0 }],
+		}];
+
+		let path = std::env::temp_dir().join(format!("siedit_roundtrip_{}.se", std::process::id()));
+		write_sie_file(&path, &accounts, &verifications).expect("write_sie_file should succeed");
+
+		let raw = fs::read(&path).expect("written file should be readable");
+		fs::remove_file(&path).ok();
+
+		let (decoded, _, had_errors) = encoding_rs::WINDOWS_1252.decode(&raw);
+
+		assert!(!had_errors);
+		assert!(decoded.contains("Försäljning Åkerby, Örnsköldsvik"));
+		assert!(decoded.contains("Öppningsbalans för källaren"));
+	}
+}